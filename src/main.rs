@@ -1,13 +1,18 @@
 use clap::{App, Arg};
 use reqwest::Error;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
 use tokio::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Semaphore};
 use tokio::task;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct ServerData {
     hostname: String,
     country_code: String,
@@ -30,23 +35,136 @@ struct ServerData {
     socks_port: Option<u16>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct StatusMessage {
     message: String,
     timestamp: String,
 }
 
-#[derive(Debug, Clone)]
+/// A composable set of criteria matched against `ServerData`. Unset fields
+/// are ignored, so `Filter::default()` matches every server.
+#[derive(Debug, Clone, Default)]
+struct Filter {
+    provider: Option<String>,
+    owned: Option<bool>,
+    city: Option<String>,
+    active: Option<bool>,
+}
+
+impl Filter {
+    fn matches(&self, server: &ServerData) -> bool {
+        if let Some(provider) = &self.provider {
+            if &server.provider != provider {
+                return false;
+            }
+        }
+        if let Some(owned) = self.owned {
+            if server.owned != owned {
+                return false;
+            }
+        }
+        if let Some(city) = &self.city {
+            let matches_code = server.city_code.as_deref() == Some(city.as_str());
+            let matches_name = server.city_name.eq_ignore_ascii_case(city);
+            if !matches_code && !matches_name {
+                return false;
+            }
+        }
+        if let Some(active) = self.active {
+            if server.active != active {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Merges a repeated `key=value` pair (from `--filter`) into the filter,
+    /// overriding any value already set for that key.
+    fn apply(&mut self, key: &str, value: &str) {
+        match key {
+            "provider" => self.provider = Some(value.to_owned()),
+            "owned" => self.owned = value.parse::<bool>().ok(),
+            "city" => self.city = Some(value.to_owned()),
+            "active" => self.active = value.parse::<bool>().ok(),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct ResultData {
     hostname: String,
     city: String,
     country: String,
     server_type: Option<String>,
     ip: String,
+    family: AddressFamily,
+    #[serde(rename = "avg_ms")]
     avg: f64,
     network_port_speed: u32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum AddressFamily {
+    V4,
+    V6,
+}
+
+impl AddressFamily {
+    fn parse(value: &str) -> Option<AddressFamily> {
+        match value {
+            "4" => Some(AddressFamily::V4),
+            "6" => Some(AddressFamily::V6),
+            "auto" => None,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ProbeMode {
+    Icmp,
+    Tcp,
+}
+
+impl ProbeMode {
+    fn parse(value: &str) -> ProbeMode {
+        match value {
+            "tcp" => ProbeMode::Tcp,
+            _ => ProbeMode::Icmp,
+        }
+    }
+}
+
+/// Knobs that control how a single server is probed, bundled together so
+/// `find_best_server` doesn't have to take them as separate positional args.
+#[derive(Debug, Clone, Copy)]
+struct ProbeOptions {
+    pings: usize,
+    interval: f64,
+    family: Option<AddressFamily>,
+    probe_mode: ProbeMode,
+    tcp_port: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Jsonl,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> OutputFormat {
+        match value {
+            "json" => OutputFormat::Json,
+            "jsonl" => OutputFormat::Jsonl,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let matches = App::new("mullscan")
@@ -70,6 +188,7 @@ async fn main() -> Result<(), Error> {
                 .long("type")
                 .value_name("type")
                 .help("The type of server to query (openvpn, bridge, wireguard, all)")
+                .possible_values(["openvpn", "bridge", "wireguard", "all"])
                 .default_value("all")
                 .takes_value(true),
         )
@@ -118,6 +237,95 @@ async fn main() -> Result<(), Error> {
                 .default_value("all")
                 .takes_value(true),
         )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("format")
+                .help("Output format (text, json, jsonl)")
+                .possible_values(["text", "json", "jsonl"])
+                .default_value("text")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("family")
+                .long("family")
+                .value_name("family")
+                .help("Address family to probe (4, 6, auto)")
+                .possible_values(["4", "6", "auto"])
+                .default_value("auto")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("probe")
+                .long("probe")
+                .value_name("mode")
+                .help("Latency probe to use (icmp, tcp)")
+                .possible_values(["icmp", "tcp"])
+                .default_value("icmp")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("tcp_port")
+                .long("tcp-port")
+                .value_name("port")
+                .help("Port to connect to when --probe tcp is used")
+                .default_value("443")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("concurrency")
+                .long("concurrency")
+                .value_name("n")
+                .help("Maximum number of servers to probe at once")
+                .default_value("50")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("refresh")
+                .long("refresh")
+                .help("Bypass the relay list cache and re-fetch from api.mullvad.net"),
+        )
+        .arg(
+            Arg::new("cache_ttl")
+                .long("cache-ttl")
+                .value_name("seconds")
+                .help("How long a cached relay list stays valid (default 600)")
+                .default_value("600")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("provider")
+                .long("provider")
+                .value_name("name")
+                .help("Only show servers from this hosting provider")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("owned")
+                .long("owned")
+                .help("Only show Mullvad-owned servers"),
+        )
+        .arg(
+            Arg::new("city")
+                .long("city")
+                .value_name("city")
+                .help("Only show servers in this city (code or name)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("active")
+                .long("active")
+                .help("Only show active servers"),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .value_name("key=value")
+                .help("Additional filter, repeatable and AND-combined (provider, owned, city, active)")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
         .get_matches();
 
     let country = matches.value_of("country").map(|c| c.to_owned());
@@ -126,7 +334,8 @@ async fn main() -> Result<(), Error> {
         .value_of("interval")
         .unwrap()
         .parse::<f64>()
-        .unwrap_or(0.2);
+        .unwrap_or(0.2)
+        .max(0.2);
     let pings = matches
         .value_of("pings")
         .unwrap()
@@ -143,25 +352,79 @@ async fn main() -> Result<(), Error> {
         .parse::<u32>()
         .unwrap_or(0);
     let run_mode = matches.value_of("run_mode").unwrap().to_owned();
+    let format = OutputFormat::parse(matches.value_of("format").unwrap());
+    let family = AddressFamily::parse(matches.value_of("family").unwrap());
+    let probe_mode = ProbeMode::parse(matches.value_of("probe").unwrap());
+    let tcp_port = matches
+        .value_of("tcp_port")
+        .unwrap()
+        .parse::<u16>()
+        .unwrap_or(443);
+    let probe_options = ProbeOptions {
+        pings,
+        interval,
+        family,
+        probe_mode,
+        tcp_port,
+    };
+    let concurrency = matches
+        .value_of("concurrency")
+        .unwrap()
+        .parse::<usize>()
+        .unwrap_or(50)
+        .max(1);
+    let refresh = matches.is_present("refresh");
+    let cache_ttl = Duration::from_secs(
+        matches
+            .value_of("cache_ttl")
+            .unwrap()
+            .parse::<u64>()
+            .unwrap_or(600),
+    );
 
-    let server_data = fetch_server_data(&server_type).await?;
+    let mut filter = Filter {
+        provider: matches.value_of("provider").map(|s| s.to_owned()),
+        owned: matches.is_present("owned").then_some(true),
+        city: matches.value_of("city").map(|s| s.to_owned()),
+        active: matches.is_present("active").then_some(true),
+    };
+    if let Some(pairs) = matches.values_of("filter") {
+        for pair in pairs {
+            if let Some((key, value)) = pair.split_once('=') {
+                filter.apply(key, value);
+            }
+        }
+    }
+
+    let server_data = fetch_server_data(&server_type, refresh, cache_ttl).await?;
 
     if matches.is_present("list_countries") {
         list_countries(&server_data);
     } else {
         let (tx, mut rx) = mpsc::channel::<ResultData>(10);
+        let semaphore = Arc::new(Semaphore::new(concurrency));
 
         for server in server_data {
             let tx = tx.clone();
             let server = server.clone();
             let country = country.clone();
             let run_mode = run_mode.clone();
+            let filter = filter.clone();
+            let semaphore = semaphore.clone();
             task::spawn(async move {
-                if let Some(result) =
-                    find_best_server(&server, &country, port_speed, &run_mode, pings, interval)
-                        .await
-                {
-                    let _ = tx.send(result).await;
+                if let Ok(_permit) = semaphore.acquire_owned().await {
+                    if let Some(result) = find_best_server(
+                        &server,
+                        &country,
+                        port_speed,
+                        &run_mode,
+                        &filter,
+                        &probe_options,
+                    )
+                    .await
+                    {
+                        let _ = tx.send(result).await;
+                    }
                 }
             });
         }
@@ -175,19 +438,58 @@ async fn main() -> Result<(), Error> {
 
         results.sort_by(|a, b| a.avg.partial_cmp(&b.avg).unwrap_or(Ordering::Equal));
         results.truncate(top_n);
-        display_top_servers(&results, top_n);
+        display_top_servers(&results, top_n, format);
     }
 
     Ok(())
 }
 
-async fn fetch_server_data(server_type: &str) -> Result<Vec<ServerData>, Error> {
+async fn fetch_server_data(
+    server_type: &str,
+    refresh: bool,
+    cache_ttl: Duration,
+) -> Result<Vec<ServerData>, Error> {
+    let cache_path = relay_cache_path(server_type);
+
+    if !refresh {
+        if let Some(cached) = read_relay_cache(&cache_path, cache_ttl) {
+            return Ok(cached);
+        }
+    }
+
     let url = format!("https://api.mullvad.net/www/relays/{}/", server_type);
     let response = reqwest::get(url).await?;
     let server_data: Vec<ServerData> = response.json().await?;
+    write_relay_cache(&cache_path, &server_data);
     Ok(server_data)
 }
 
+/// Path of the on-disk relay list cache for a given `server_type`, under the
+/// user's cache directory (falling back to the system temp dir if unknown).
+fn relay_cache_path(server_type: &str) -> PathBuf {
+    let base = dirs::cache_dir().unwrap_or_else(std::env::temp_dir);
+    base.join("mullscan").join(format!("{}.json", server_type))
+}
+
+/// Returns the cached relay list if `path` exists and is younger than `ttl`.
+fn read_relay_cache(path: &Path, ttl: Duration) -> Option<Vec<ServerData>> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    if modified.elapsed().ok()? >= ttl {
+        return None;
+    }
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_relay_cache(path: &Path, server_data: &[ServerData]) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string(server_data) {
+        let _ = fs::write(path, json);
+    }
+}
+
 fn list_countries(server_data: &[ServerData]) {
     let mut countries = HashSet::new();
     for server in server_data {
@@ -208,29 +510,70 @@ async fn find_best_server(
     country: &Option<String>,
     port_speed: u32,
     run_mode: &str,
-    pings: usize,
-    interval: f64,
+    filter: &Filter,
+    probe: &ProbeOptions,
 ) -> Option<ResultData> {
     if (country.is_none() || country.as_ref().unwrap() == &server.country_code)
         && (server.network_port_speed >= port_speed)
         && check_run_mode(server.stboot, run_mode)
+        && filter.matches(server)
     {
-        let avg = ping(&server.ipv4_addr_in, pings, interval).await;
-        if let Some(avg) = avg {
-            return Some(ResultData {
-                hostname: server.hostname.clone(),
-                city: server.city_name.clone(),
-                country: server.country_name.clone(),
-                server_type: server.server_type.clone(),
-                ip: server.ipv4_addr_in.clone(),
-                avg,
-                network_port_speed: server.network_port_speed,
-            });
+        for (candidate_family, ip) in candidate_addresses(server, probe.family) {
+            let avg = match probe.probe_mode {
+                ProbeMode::Icmp => ping(&ip, probe.pings, probe.interval, candidate_family).await,
+                ProbeMode::Tcp => {
+                    tcp_probe(
+                        &ip,
+                        probe.tcp_port,
+                        probe.pings,
+                        probe.interval,
+                        candidate_family,
+                    )
+                    .await
+                }
+            };
+            if let Some(avg) = avg {
+                return Some(ResultData {
+                    hostname: server.hostname.clone(),
+                    city: server.city_name.clone(),
+                    country: server.country_name.clone(),
+                    server_type: server.server_type.clone(),
+                    ip,
+                    family: candidate_family,
+                    avg,
+                    network_port_speed: server.network_port_speed,
+                });
+            }
         }
     }
     None
 }
 
+/// Returns the addresses to try, in order, for the requested family.
+/// In `auto` mode (`family == None`) IPv6 is preferred when the server has
+/// one, falling back to IPv4 if the IPv6 probe doesn't succeed.
+fn candidate_addresses(
+    server: &ServerData,
+    family: Option<AddressFamily>,
+) -> Vec<(AddressFamily, String)> {
+    match family {
+        Some(AddressFamily::V4) => vec![(AddressFamily::V4, server.ipv4_addr_in.clone())],
+        Some(AddressFamily::V6) => server
+            .ipv6_addr_in
+            .clone()
+            .map(|addr| vec![(AddressFamily::V6, addr)])
+            .unwrap_or_default(),
+        None => {
+            let mut candidates = Vec::new();
+            if let Some(ipv6) = server.ipv6_addr_in.clone() {
+                candidates.push((AddressFamily::V6, ipv6));
+            }
+            candidates.push((AddressFamily::V4, server.ipv4_addr_in.clone()));
+            candidates
+        }
+    }
+}
+
 fn check_run_mode(server_stboot: bool, run_mode: &str) -> bool {
     match run_mode {
         "ram" => server_stboot,
@@ -239,8 +582,12 @@ fn check_run_mode(server_stboot: bool, run_mode: &str) -> bool {
     }
 }
 
-async fn ping(ip: &str, pings: usize, interval: f64) -> Option<f64> {
-    let output = Command::new("ping")
+async fn ping(ip: &str, pings: usize, interval: f64, family: AddressFamily) -> Option<f64> {
+    let mut command = Command::new("ping");
+    if family == AddressFamily::V6 {
+        command.arg("-6");
+    }
+    let output = command
         .arg("-c")
         .arg(pings.to_string())
         .arg("-i")
@@ -262,23 +609,253 @@ async fn ping(ip: &str, pings: usize, interval: f64) -> Option<f64> {
     None
 }
 
-fn display_top_servers(results: &[ResultData], top_n: usize) {
-    if !results.is_empty() {
-        println!("\nTop {} results:", top_n);
-        for result in results {
-            let server_type: Option<&str> = result.server_type.as_deref();
-
-            println!(
-                " - {} ({:.1}ms) {} Gbps {} {}, {}",
-                result.hostname,
-                result.avg,
-                result.network_port_speed,
-                server_type.unwrap_or("unknown"),
-                result.city,
-                result.country
-            );
+/// Measures latency without ICMP by timing `pings` TCP handshakes to `ip:port`,
+/// spaced `interval` seconds apart, and averaging the samples that connected
+/// before the per-attempt timeout.
+async fn tcp_probe(
+    ip: &str,
+    port: u16,
+    pings: usize,
+    interval: f64,
+    family: AddressFamily,
+) -> Option<f64> {
+    let addr = match family {
+        AddressFamily::V6 => format!("[{}]:{}", ip, port),
+        AddressFamily::V4 => format!("{}:{}", ip, port),
+    };
+
+    let mut samples = Vec::with_capacity(pings);
+    for attempt in 0..pings {
+        let start = Instant::now();
+        let connected = tokio::time::timeout(Duration::from_secs(2), TcpStream::connect(&addr))
+            .await
+            .ok()
+            .and_then(|r| r.ok())
+            .is_some();
+        if connected {
+            samples.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        if attempt + 1 < pings {
+            tokio::time::sleep(Duration::from_secs_f64(interval)).await;
         }
+    }
+
+    if samples.is_empty() {
+        None
     } else {
+        Some(samples.iter().sum::<f64>() / samples.len() as f64)
+    }
+}
+
+fn display_top_servers(results: &[ResultData], top_n: usize, format: OutputFormat) {
+    if results.is_empty() {
         eprintln!("No servers found");
+        return;
+    }
+
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(results) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to serialize results: {}", e),
+        },
+        OutputFormat::Jsonl => {
+            for result in results {
+                match serde_json::to_string(result) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => eprintln!("Failed to serialize result: {}", e),
+                }
+            }
+        }
+        OutputFormat::Text => {
+            println!("\nTop {} results:", top_n);
+            for result in results {
+                let server_type: Option<&str> = result.server_type.as_deref();
+                let family = match result.family {
+                    AddressFamily::V4 => "v4",
+                    AddressFamily::V6 => "v6",
+                };
+
+                println!(
+                    " - {} ({:.1}ms, {}) {} Gbps {} {}, {}",
+                    result.hostname,
+                    result.avg,
+                    family,
+                    result.network_port_speed,
+                    server_type.unwrap_or("unknown"),
+                    result.city,
+                    result.country
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_server() -> ServerData {
+        ServerData {
+            hostname: "se1-wireguard".to_owned(),
+            country_code: "se".to_owned(),
+            country_name: "Sweden".to_owned(),
+            city_code: Some("sto".to_owned()),
+            city_name: "Stockholm".to_owned(),
+            active: true,
+            owned: true,
+            provider: "31173".to_owned(),
+            ipv4_addr_in: "1.2.3.4".to_owned(),
+            ipv6_addr_in: Some("2a03:1b20::1".to_owned()),
+            network_port_speed: 10,
+            stboot: true,
+            server_type: Some("wireguard".to_owned()),
+            status_messages: None,
+            pubkey: None,
+            multihop_port: None,
+            socks_name: None,
+            socks_port: None,
+        }
+    }
+
+    #[test]
+    fn read_relay_cache_returns_none_when_file_missing() {
+        let path = std::env::temp_dir().join("mullscan-test-missing.json");
+        let _ = fs::remove_file(&path);
+        assert!(read_relay_cache(&path, Duration::from_secs(600)).is_none());
+    }
+
+    #[test]
+    fn read_relay_cache_returns_data_within_ttl() {
+        let path = std::env::temp_dir().join("mullscan-test-fresh.json");
+        let servers = vec![test_server()];
+        write_relay_cache(&path, &servers);
+
+        let cached = read_relay_cache(&path, Duration::from_secs(600))
+            .expect("fresh cache entry should be returned");
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].hostname, "se1-wireguard");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_relay_cache_rejects_entries_older_than_ttl() {
+        let path = std::env::temp_dir().join("mullscan-test-stale.json");
+        write_relay_cache(&path, &[test_server()]);
+
+        // A TTL of zero means "now" is already past expiry, regardless of
+        // how fast the write above just completed.
+        assert!(read_relay_cache(&path, Duration::from_secs(0)).is_none());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn relay_cache_path_is_scoped_to_the_server_type() {
+        let path = relay_cache_path("wireguard");
+        assert_eq!(path.file_name().unwrap(), "wireguard.json");
+        assert_eq!(path.parent().unwrap().file_name().unwrap(), "mullscan");
+    }
+
+    #[test]
+    fn filter_default_matches_everything() {
+        assert!(Filter::default().matches(&test_server()));
+    }
+
+    #[test]
+    fn filter_matches_are_and_combined() {
+        let mut filter = Filter {
+            provider: Some("31173".to_owned()),
+            city: Some("sto".to_owned()),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&test_server()));
+
+        filter.owned = Some(false);
+        assert!(!filter.matches(&test_server()));
+    }
+
+    #[test]
+    fn filter_city_matches_code_or_name_case_insensitively() {
+        let filter = Filter {
+            city: Some("STOCKHOLM".to_owned()),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&test_server()));
+
+        let filter = Filter {
+            city: Some("gothenburg".to_owned()),
+            ..Filter::default()
+        };
+        assert!(!filter.matches(&test_server()));
+    }
+
+    #[test]
+    fn filter_apply_parses_recognized_keys_and_ignores_others() {
+        let mut filter = Filter::default();
+        filter.apply("provider", "31173");
+        filter.apply("owned", "false");
+        filter.apply("unknown", "ignored");
+
+        assert_eq!(filter.provider.as_deref(), Some("31173"));
+        assert_eq!(filter.owned, Some(false));
+        assert!(filter.city.is_none());
+        assert!(filter.active.is_none());
+    }
+
+    #[test]
+    fn filter_apply_overrides_a_previously_set_value() {
+        let mut filter = Filter {
+            provider: Some("old".to_owned()),
+            ..Filter::default()
+        };
+        filter.apply("provider", "new");
+        assert_eq!(filter.provider.as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn candidate_addresses_prefers_ipv6_in_auto_mode() {
+        let server = test_server();
+        let candidates = candidate_addresses(&server, None);
+        assert_eq!(
+            candidates,
+            vec![
+                (AddressFamily::V6, server.ipv6_addr_in.clone().unwrap()),
+                (AddressFamily::V4, server.ipv4_addr_in.clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn candidate_addresses_falls_back_to_ipv4_without_ipv6_support() {
+        let server = ServerData {
+            ipv6_addr_in: None,
+            ..test_server()
+        };
+        let candidates = candidate_addresses(&server, None);
+        assert_eq!(candidates, vec![(AddressFamily::V4, server.ipv4_addr_in)]);
+    }
+
+    #[test]
+    fn candidate_addresses_honors_an_explicit_family() {
+        let server = test_server();
+        assert_eq!(
+            candidate_addresses(&server, Some(AddressFamily::V4)),
+            vec![(AddressFamily::V4, server.ipv4_addr_in.clone())]
+        );
+        assert_eq!(
+            candidate_addresses(&server, Some(AddressFamily::V6)),
+            vec![(AddressFamily::V6, server.ipv6_addr_in.clone().unwrap())]
+        );
+    }
+
+    #[test]
+    fn candidate_addresses_skips_a_server_without_ipv6_when_family_is_v6() {
+        let server = ServerData {
+            ipv6_addr_in: None,
+            ..test_server()
+        };
+        assert!(candidate_addresses(&server, Some(AddressFamily::V6)).is_empty());
     }
 }